@@ -0,0 +1,42 @@
+//! Minimal CRC32 (IEEE 802.3, the variant used by zip/gzip and file-patch
+//! distribution tools) for verifying that a patch is applied to, and produces,
+//! the expected ROM image.
+
+/// Computes the CRC32 of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut hasher = Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Incremental CRC32 accumulator, for hashing data that arrives in chunks
+/// (e.g. a file streamed through a `BufReader`) without buffering it whole.
+pub struct Hasher {
+    crc: u32,
+}
+
+impl Hasher {
+    pub fn new() -> Self {
+        Hasher { crc: 0xFFFF_FFFF }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.crc ^= byte as u32;
+            for _ in 0..8 {
+                let mask = (self.crc & 1).wrapping_neg();
+                self.crc = (self.crc >> 1) ^ (0xEDB8_8320 & mask);
+            }
+        }
+    }
+
+    pub fn finalize(self) -> u32 {
+        !self.crc
+    }
+}
+
+impl Default for Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
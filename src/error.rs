@@ -5,6 +5,13 @@ pub enum IpsError {
     #[error("Invalid Patch: `{0}")]
     InvalidPatch(String),
 
+    #[error("CRC32 mismatch for {which} ROM: expected {expected:#010x}, got {actual:#010x}")]
+    ChecksumMismatch {
+        which: &'static str,
+        expected: u32,
+        actual: u32,
+    },
+
     #[error("Bad IO")]
     Io(#[from] std::io::Error),
 
@@ -18,17 +18,76 @@ enum Record {
     },
 }
 
+/// A single patch operation exposed to library callers.
+///
+/// Unlike the internal [`Record`] representation, a `Hunk` borrows from its
+/// parent [`Patch`] so that embedders can walk the patch (e.g. to apply it
+/// incrementally against a seekable file) without copying record payloads.
 #[derive(Debug)]
-struct Patch {
+pub enum Hunk<'a> {
+    /// A literal run of bytes to write at `offset`.
+    Patch { offset: usize, payload: &'a [u8] },
+    /// A run-length-encoded fill of `len` copies of `value` at `offset`.
+    Fill {
+        offset: usize,
+        len: usize,
+        value: u8,
+    },
+}
+
+impl<'a> Hunk<'a> {
+    /// Absolute output offset this hunk writes to.
+    pub fn offset(&self) -> usize {
+        match *self {
+            Hunk::Patch { offset, .. } => offset,
+            Hunk::Fill { offset, .. } => offset,
+        }
+    }
+
+    /// Number of bytes this hunk writes.
+    pub fn len(&self) -> usize {
+        match *self {
+            Hunk::Patch { payload, .. } => payload.len(),
+            Hunk::Fill { len, .. } => len,
+        }
+    }
+
+    /// Returns `true` if this hunk writes no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Literal payload for a [`Hunk::Patch`], or `None` for an RLE fill.
+    pub fn payload(&self) -> Option<&'a [u8]> {
+        match *self {
+            Hunk::Patch { payload, .. } => Some(payload),
+            Hunk::Fill { .. } => None,
+        }
+    }
+
+    /// Repeated byte for a [`Hunk::Fill`], or `None` for a literal patch.
+    pub fn value(&self) -> Option<u8> {
+        match *self {
+            Hunk::Fill { value, .. } => Some(value),
+            Hunk::Patch { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Patch {
     records: Vec<Record>,
+    truncation: Option<usize>,
 }
 
 impl Patch {
-    fn load_pathbuf(patch_filename: &Path) -> Result<Self> {
+    /// Loads and parses a patch from a filesystem path.
+    pub fn load_pathbuf(patch_filename: &Path) -> Result<Self> {
         Self::load(patch_filename.to_str().ok_or(IpsError::InvalidPath())?)
     }
 
-    fn load(patch_filename: &str) -> Result<Self> {
+    /// Loads and parses a patch from a filesystem path given as a `&str`.
+    pub fn load(patch_filename: &str) -> Result<Self> {
         let buf = {
             let mut f = std::fs::File::open(patch_filename)?;
 
@@ -41,58 +100,75 @@ impl Patch {
         Patch::parse(&buf)
     }
 
-    fn parse(patch: &[u8]) -> Result<Self> {
+    /// Parses a patch from an in-memory IPS byte stream.
+    pub fn parse(patch: &[u8]) -> Result<Self> {
         if patch.len() < 5 || &patch[..5] != "PATCH".as_bytes() {
-            "Missing PATCH header".to_string();
+            return Err(IpsError::InvalidPatch("Missing PATCH header".to_string()).into());
         }
         let mut patch = &patch[5..];
 
         let mut records = Vec::new();
+        let mut truncation = None;
 
         loop {
-            if patch.len() == 3 && &patch[..3] == "EOF".as_bytes() {
+            if patch.len() >= 3 && &patch[..3] == "EOF".as_bytes() {
+                // Optional IPS truncation extension: a 3-byte big-endian length
+                // the output should be cut to, appended after the EOF marker.
+                let rest = &patch[3..];
+                if rest.len() == 3 {
+                    truncation = Some(
+                        (((rest[0] as u32) << 16) + ((rest[1] as u32) << 8) + (rest[2] as u32))
+                            as usize,
+                    );
+                } else if !rest.is_empty() {
+                    return Err(IpsError::InvalidPatch(format!(
+                        "Expecting 3-byte truncation field after EOF, got {} bytes",
+                        rest.len()
+                    ))
+                    .into());
+                }
                 break;
             }
 
             if patch.len() < 3 {
-                IpsError::InvalidPatch {
-                    0: format!(
-                        "Expecting record 'offset' field, got {} of 3 bytes \
-                                          before reaching end of file",
-                        patch.len()
-                    ),
-                };
+                return Err(IpsError::InvalidPatch(format!(
+                    "Expecting record 'offset' field, got {} of 3 bytes \
+                     before reaching end of file",
+                    patch.len()
+                ))
+                .into());
             }
             let offset = ((patch[0] as u32) << 16) + ((patch[1] as u32) << 8) + (patch[2] as u32);
             patch = &patch[3..];
 
             if patch.len() < 2 {
-                IpsError::InvalidPatch {
-                    0: format!(
-                        "Expecting record 'size' field, got {} of 2 bytes before \
-                                          reaching end of file",
-                        patch.len()
-                    ),
-                };
+                return Err(IpsError::InvalidPatch(format!(
+                    "Expecting record 'size' field, got {} of 2 bytes before \
+                     reaching end of file",
+                    patch.len()
+                ))
+                .into());
             }
             let size = ((patch[0] as u16) << 8) + (patch[1] as u16);
             patch = &patch[2..];
 
             records.push(if 0 == size {
                 if patch.len() < 2 {
-                    IpsError::InvalidPatch {
-                        0: format!(
-                            "Expecting record 'rle_size', got {} of 2 bytes \
-                                              before reaching end of file",
-                            patch.len()
-                        ),
-                    };
+                    return Err(IpsError::InvalidPatch(format!(
+                        "Expecting record 'rle_size', got {} of 2 bytes \
+                         before reaching end of file",
+                        patch.len()
+                    ))
+                    .into());
                 }
                 let rle_size = ((patch[0] as u16) << 8) + (patch[1] as u16);
                 patch = &patch[2..];
 
                 if patch.is_empty() {
-                    "Expecting record 'rle_value' field, got end of file".to_string();
+                    return Err(IpsError::InvalidPatch(
+                        "Expecting record 'rle_value' field, got end of file".to_string(),
+                    )
+                    .into());
                 }
 
                 let rle_value = patch[0];
@@ -105,14 +181,13 @@ impl Patch {
                 }
             } else {
                 if patch.len() < size as usize {
-                    IpsError::InvalidPatch {
-                        0: format!(
-                            "Expecting record 'data' field, got {} of {} bytes \
-                                              before reaching end of file",
-                            patch.len(),
-                            size
-                        ),
-                    };
+                    return Err(IpsError::InvalidPatch(format!(
+                        "Expecting record 'data' field, got {} of {} bytes \
+                         before reaching end of file",
+                        patch.len(),
+                        size
+                    ))
+                    .into());
                 }
                 let data = Vec::from(&patch[..(size as usize)]);
                 patch = &patch[(size as usize)..];
@@ -126,7 +201,10 @@ impl Patch {
 
         // records.sort();
 
-        let p = Patch { records };
+        let p = Patch {
+            records,
+            truncation,
+        };
         Ok(p)
     }
 
@@ -154,7 +232,29 @@ impl Patch {
         }
     }
 
-    fn apply(&self, ibuf: &[u8]) -> Result<Vec<u8>> {
+    /// Iterates the patch's operations as public [`Hunk`]s, allowing callers
+    /// to apply them incrementally (e.g. against a seekable file) rather than
+    /// buffering the whole output via [`Patch::apply`].
+    pub fn hunks(&self) -> impl Iterator<Item = Hunk<'_>> {
+        self.records.iter().map(|rec| match *rec {
+            Record::Normal { offset, ref data } => Hunk::Patch {
+                offset,
+                payload: data,
+            },
+            Record::RuntimeLengthEncoded {
+                offset,
+                size,
+                value,
+            } => Hunk::Fill {
+                offset,
+                len: size,
+                value,
+            },
+        })
+    }
+
+    /// Applies the patch to `ibuf`, returning the patched output buffer.
+    pub fn apply(&self, ibuf: &[u8]) -> Result<Vec<u8>> {
         let mut obuf = ibuf.to_vec();
         for rec in self.records.iter() {
             match *rec {
@@ -162,57 +262,436 @@ impl Patch {
                     ref offset,
                     ref data,
                 } => {
-                    // Special case: extend existing ROM data.
-                    if obuf.len() == *offset {
-                        obuf.extend_from_slice(data);
-                        continue;
-                    }
-                    if ibuf.len() < *offset + data.len() {
-                        IpsError::InvalidPatch {
-                            0: format!(
-                                "Normal record with offset {}, size {} is out of \
-                                                  bounds",
-                                offset,
-                                data.len()
-                            ),
-                        };
+                    // A record may append to or straddle the current end of the
+                    // output (ROM expansion); only a gap past the end is invalid.
+                    if *offset > obuf.len() {
+                        return Err(IpsError::InvalidPatch(format!(
+                            "Normal record with offset {}, size {} is out of bounds",
+                            offset,
+                            data.len()
+                        ))
+                        .into());
                     }
-                    for i in 0..data.len() {
-                        obuf[*offset + i] = data[i];
+                    let end = *offset + data.len();
+                    if end > obuf.len() {
+                        obuf.resize(end, 0);
                     }
+                    obuf[*offset..end].copy_from_slice(data);
                 }
                 Record::RuntimeLengthEncoded {
                     ref offset,
                     ref size,
                     ref value,
                 } => {
-                    // Special case: extend existing ROM data.
-                    if obuf.len() == *offset {
-                        for _i in 0..*size {
-                            obuf.push(*value);
-                        }
-                        continue;
+                    // As above: growth by appending or straddling is allowed.
+                    if *offset > obuf.len() {
+                        return Err(IpsError::InvalidPatch(format!(
+                            "RLE record with offset {}, size {} is out of bounds",
+                            offset, size
+                        ))
+                        .into());
                     }
-                    if ibuf.len() < offset + size {
-                        IpsError::InvalidPatch {
-                            0: format!(
-                                "RLE record with offset {}, size {} is out of \
-                                                  bounds",
-                                offset, size
-                            ),
-                        };
+                    let end = *offset + *size;
+                    if end > obuf.len() {
+                        obuf.resize(end, *value);
                     }
-                    for i in *offset..(*offset + *size) {
-                        obuf[i] = *value;
+                    for b in obuf.iter_mut().skip(*offset).take(*size) {
+                        *b = *value;
                     }
                 }
             }
         }
+        if let Some(len) = self.truncation {
+            obuf.truncate(len);
+        }
         Ok(obuf)
     }
+
+    /// Applies the patch, optionally verifying the CRC32 of the source ROM
+    /// before patching and of the produced ROM afterwards.
+    ///
+    /// A mismatch on the source usually means the patch is being applied to
+    /// the wrong ROM revision; a mismatch on the output means the patch did
+    /// not reproduce the expected image.
+    pub fn apply_checked(
+        &self,
+        ibuf: &[u8],
+        expected_src_crc: Option<u32>,
+        expected_dst_crc: Option<u32>,
+    ) -> Result<Vec<u8>> {
+        if let Some(expected) = expected_src_crc {
+            let actual = crate::crc32::crc32(ibuf);
+            if actual != expected {
+                return Err(IpsError::ChecksumMismatch {
+                    which: "source",
+                    expected,
+                    actual,
+                }
+                .into());
+            }
+        }
+
+        let obuf = self.apply(ibuf)?;
+
+        if let Some(expected) = expected_dst_crc {
+            let actual = crate::crc32::crc32(&obuf);
+            if actual != expected {
+                return Err(IpsError::ChecksumMismatch {
+                    which: "output",
+                    expected,
+                    actual,
+                }
+                .into());
+            }
+        }
+
+        Ok(obuf)
+    }
+
+    /// Applies the patch to `input`, writing the result to `output` without
+    /// holding the whole ROM in memory.
+    ///
+    /// The output is seeded with a streamed copy of the input (records carry
+    /// absolute offsets) and only the patched regions are then seeked to and
+    /// overwritten. The RLE and trailing-extend cases of [`Patch::apply`] map
+    /// onto `seek`+`write` and, for truncation, `set_len`.
+    pub fn apply_to_file(&self, input: &Path, output: &Path) -> Result<()> {
+        use std::io::{BufReader, BufWriter, Seek, SeekFrom};
+
+        let input_len = std::fs::metadata(input)?.len() as usize;
+
+        {
+            let mut reader = BufReader::new(std::fs::File::open(input)?);
+            let mut writer = BufWriter::new(std::fs::File::create(output)?);
+            std::io::copy(&mut reader, &mut writer)?;
+            writer.flush()?;
+        }
+
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(output)?;
+        let mut writer = BufWriter::new(file);
+
+        // Track the growing output length so we can tell legal growth (a
+        // record starting at or before the current end, possibly straddling
+        // it) from a record that would leave a gap past it, mirroring
+        // `apply`'s in-memory checks.
+        let mut cur_len = input_len;
+        for hunk in self.hunks() {
+            let offset = hunk.offset();
+            let len = hunk.len();
+
+            if offset > cur_len {
+                return Err(IpsError::InvalidPatch(format!(
+                    "{} record with offset {}, size {} is out of bounds",
+                    if hunk.payload().is_some() {
+                        "Normal"
+                    } else {
+                        "RLE"
+                    },
+                    offset,
+                    len
+                ))
+                .into());
+            }
+            cur_len = cur_len.max(offset + len);
+
+            writer.seek(SeekFrom::Start(offset as u64))?;
+            match hunk {
+                Hunk::Patch { payload, .. } => writer.write_all(payload)?,
+                Hunk::Fill { len, value, .. } => {
+                    let chunk = vec![value; len.min(8192)];
+                    let mut remaining = len;
+                    while remaining > 0 {
+                        let n = remaining.min(chunk.len());
+                        writer.write_all(&chunk[..n])?;
+                        remaining -= n;
+                    }
+                }
+            }
+        }
+        writer.flush()?;
+
+        // Truncation only ever shrinks, matching `obuf.truncate` in `apply`.
+        if let Some(len) = self.truncation {
+            let size = writer.get_ref().metadata()?.len();
+            if (len as u64) < size {
+                writer.get_ref().set_len(len as u64)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the patch against a known input length without applying it.
+    ///
+    /// Flags offsets beyond the 0xFFFFFF addressable range, records that start
+    /// past the end of the input as grown by lower records (leaving a gap, a
+    /// telltale sign of the wrong ROM revision), and records whose writes
+    /// overlap one another. Records that append to or straddle the end of the
+    /// input are accepted.
+    pub fn validate(&self, input_len: usize) -> Result<()> {
+        const MAX_OFFSET: usize = 0xFFFFFF;
+
+        let mut ranges: Vec<(usize, usize)> = Vec::with_capacity(self.records.len());
+        for rec in self.records.iter() {
+            let (offset, len) = match *rec {
+                Record::Normal { offset, ref data } => (offset, data.len()),
+                Record::RuntimeLengthEncoded { offset, size, .. } => (offset, size),
+            };
+
+            if offset > MAX_OFFSET {
+                return Err(IpsError::InvalidPatch(format!(
+                    "record offset {:#x} exceeds the 0xFFFFFF addressable range",
+                    offset
+                ))
+                .into());
+            }
+
+            ranges.push((offset, offset + len));
+        }
+
+        ranges.sort_unstable();
+        for pair in ranges.windows(2) {
+            if pair[0].1 > pair[1].0 {
+                return Err(IpsError::InvalidPatch(format!(
+                    "overlapping writes: [{}, {}) and [{}, {})",
+                    pair[0].0, pair[0].1, pair[1].0, pair[1].1
+                ))
+                .into());
+            }
+        }
+
+        // Growth by appending or straddling the end is legal, but a record
+        // starting beyond the reachable end (as grown by lower records) leaves
+        // an unwritten gap and is rejected.
+        let mut reachable = input_len;
+        for &(start, end) in ranges.iter() {
+            if start > reachable {
+                return Err(IpsError::InvalidPatch(format!(
+                    "record at offset {} starts past the reachable end {} (gap)",
+                    start, reachable
+                ))
+                .into());
+            }
+            if end > reachable {
+                reachable = end;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds a patch that transforms `original` into `modified`.
+    ///
+    /// The two buffers are scanned in lockstep and maximal runs of differing
+    /// bytes are grouped into records. Long homogeneous runs are emitted as
+    /// RLE records when that is smaller than a literal record. Offsets and the
+    /// truncation length are 3-byte big-endian, so a differing byte or a
+    /// truncation length past `0xFFFFFF` cannot be represented and yields an
+    /// error.
+    pub fn create(original: &[u8], modified: &[u8]) -> Result<Self> {
+        let mut records = Vec::new();
+
+        let mut i = 0;
+        while i < modified.len() {
+            // Skip bytes that are identical to the original.
+            if i < original.len() && original[i] == modified[i] {
+                i += 1;
+                continue;
+            }
+
+            // Accumulate a maximal run of differing bytes.
+            let start = i;
+            while i < modified.len() && !(i < original.len() && original[i] == modified[i]) {
+                i += 1;
+            }
+
+            Self::emit_run(&mut records, start, &modified[start..i], modified, original.len())?;
+        }
+
+        // Applying a patch seeds the output from the full-length input, so a
+        // shorter target can only be reproduced with a truncation record.
+        let truncation = if modified.len() < original.len() {
+            if modified.len() > 0xFFFFFF {
+                return Err(IpsError::InvalidPatch(format!(
+                    "truncation length {:#x} exceeds the 0xFFFFFF addressable range",
+                    modified.len()
+                ))
+                .into());
+            }
+            Some(modified.len())
+        } else {
+            None
+        };
+
+        Ok(Patch {
+            records,
+            truncation,
+        })
+    }
+
+    /// Splits a differing run into records, choosing RLE where it is smaller,
+    /// honouring the 2-byte size limit, splitting at the `original_len`
+    /// boundary so any appended tail grows the output from exactly that
+    /// offset, and shifting any record whose offset would collide with the
+    /// `EOF` marker.
+    fn emit_run(
+        records: &mut Vec<Record>,
+        offset: usize,
+        run: &[u8],
+        modified: &[u8],
+        original_len: usize,
+    ) -> Result<()> {
+        // "EOF" interpreted as a 3-byte big-endian offset.
+        const EOF_OFFSET: usize = 0x454F46;
+        // Offsets are 3 bytes, record sizes 2 bytes.
+        const MAX_OFFSET: usize = 0xFFFFFF;
+        const MAX_SIZE: usize = 0xFFFF;
+        // A literal record costs 5 + len bytes; an RLE record is a flat 8, so
+        // RLE only pays off once a homogeneous run is longer than 3 bytes.
+        const RLE_MIN_RUN: usize = 4;
+
+        let mut idx = 0;
+        while idx < run.len() {
+            let abs = offset + idx;
+            if abs > MAX_OFFSET {
+                return Err(IpsError::InvalidPatch(format!(
+                    "record offset {:#x} exceeds the 0xFFFFFF addressable range",
+                    abs
+                ))
+                .into());
+            }
+
+            // A record whose offset is exactly "EOF" would be mistaken for the
+            // end marker, so shift it back one byte and carry the (unchanged)
+            // preceding byte along as a literal. This is only safe at the start
+            // of the run, where that byte belongs to no other record; interior
+            // collisions are avoided by the boundary adjustment below, so
+            // `abs == EOF_OFFSET` implies `idx == 0`.
+            let lead = if abs == EOF_OFFSET {
+                Some(modified[abs - 1])
+            } else {
+                None
+            };
+            let extra = lead.is_some() as usize;
+            let rec_offset = abs - extra;
+
+            // Bytes this record may consume, capped by the size field and split
+            // at the original length so the appended tail is its own record.
+            let mut avail = (run.len() - idx).min(MAX_SIZE - extra);
+            if abs < original_len {
+                avail = avail.min(original_len - abs);
+            }
+
+            // Length of the homogeneous run starting at `idx`.
+            let value = run[idx];
+            let mut same = 1;
+            while idx + same < run.len() && run[idx + same] == value {
+                same += 1;
+            }
+
+            let is_rle = same >= RLE_MIN_RUN && (lead.is_none() || lead == Some(value));
+            let mut take = if is_rle {
+                same.min(avail)
+            } else {
+                // Literal chunk: grow until a homogeneous run worth its own RLE
+                // record, or a limit, is reached.
+                let mut end = idx + 1;
+                while end - idx < avail {
+                    let b = run[end];
+                    let mut h = 1;
+                    while end + h < run.len() && run[end + h] == b {
+                        h += 1;
+                    }
+                    if h >= RLE_MIN_RUN {
+                        break;
+                    }
+                    end += 1;
+                }
+                end - idx
+            };
+
+            // Keep the next chunk from beginning exactly at "EOF": absorb the
+            // colliding byte where possible, otherwise stop one byte short.
+            if idx + take < run.len() && abs + take == EOF_OFFSET {
+                let hard_cap = (run.len() - idx).min(MAX_SIZE - extra);
+                if take < hard_cap && (!is_rle || take < same) {
+                    take += 1;
+                } else if take > 1 {
+                    take -= 1;
+                }
+            }
+
+            if is_rle {
+                records.push(Record::RuntimeLengthEncoded {
+                    offset: rec_offset,
+                    size: take + extra,
+                    value,
+                });
+            } else {
+                let mut data = Vec::with_capacity(take + extra);
+                if let Some(l) = lead {
+                    data.push(l);
+                }
+                data.extend_from_slice(&run[idx..idx + take]);
+                records.push(Record::Normal {
+                    offset: rec_offset,
+                    data,
+                });
+            }
+            idx += take;
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the patch as an IPS byte stream (`PATCH` header, records,
+    /// `EOF` trailer, and the optional truncation field).
+    pub fn write<W: Write>(&self, w: &mut W) -> Result<()> {
+        fn be3(n: usize) -> [u8; 3] {
+            [(n >> 16) as u8, (n >> 8) as u8, n as u8]
+        }
+        fn be2(n: usize) -> [u8; 2] {
+            [(n >> 8) as u8, n as u8]
+        }
+
+        w.write_all(b"PATCH")?;
+        for rec in self.records.iter() {
+            match *rec {
+                Record::Normal { offset, ref data } => {
+                    w.write_all(&be3(offset))?;
+                    w.write_all(&be2(data.len()))?;
+                    w.write_all(data)?;
+                }
+                Record::RuntimeLengthEncoded {
+                    offset,
+                    size,
+                    value,
+                } => {
+                    w.write_all(&be3(offset))?;
+                    w.write_all(&be2(0))?;
+                    w.write_all(&be2(size))?;
+                    w.write_all(&[value])?;
+                }
+            }
+        }
+        w.write_all(b"EOF")?;
+        if let Some(len) = self.truncation {
+            w.write_all(&be3(len))?;
+        }
+
+        Ok(())
+    }
 }
 
-pub fn patch(patch_filename: &Path) -> Result<()> {
+pub fn patch(
+    patch_filename: &Path,
+    strict: bool,
+    verify_in: Option<u32>,
+    verify_out: Option<u32>,
+) -> Result<()> {
     let patch = Patch::load_pathbuf(patch_filename)?;
 
     let ibuf = {
@@ -227,7 +706,12 @@ pub fn patch(patch_filename: &Path) -> Result<()> {
         x
     };
 
-    let obuf = patch.apply(&ibuf)?;
+    if strict {
+        patch.validate(ibuf.len())?;
+    }
+
+    let obuf = patch.apply_checked(&ibuf, verify_in, verify_out)?;
+    eprintln!("output CRC32: {:#010x}", crate::crc32::crc32(&obuf));
 
     if std::io::stdout()
         .write_all(&obuf)
@@ -240,5 +724,207 @@ pub fn patch(patch_filename: &Path) -> Result<()> {
     Ok(())
 }
 
+pub fn patch_stream(
+    patch_filename: &Path,
+    input: &Path,
+    output: &Path,
+    strict: bool,
+    verify_in: Option<u32>,
+    verify_out: Option<u32>,
+) -> Result<()> {
+    let patch = Patch::load_pathbuf(patch_filename)?;
+
+    if strict {
+        let input_len = std::fs::metadata(input)?.len() as usize;
+        patch.validate(input_len)?;
+    }
+
+    if let Some(expected) = verify_in {
+        let actual = crc32_file(input)?;
+        if actual != expected {
+            return Err(IpsError::ChecksumMismatch {
+                which: "source",
+                expected,
+                actual,
+            }
+            .into());
+        }
+    }
+
+    patch.apply_to_file(input, output)?;
+
+    let actual_out = crc32_file(output)?;
+    if let Some(expected) = verify_out {
+        if actual_out != expected {
+            return Err(IpsError::ChecksumMismatch {
+                which: "output",
+                expected,
+                actual: actual_out,
+            }
+            .into());
+        }
+    }
+    eprintln!("output CRC32: {:#010x}", actual_out);
+
+    Ok(())
+}
+
+/// Computes the CRC32 of a file by streaming it in fixed-size chunks, without
+/// holding the whole file in memory.
+fn crc32_file(path: &Path) -> Result<u32> {
+    use std::io::BufReader;
+
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
+    let mut hasher = crate::crc32::Hasher::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    Ok(hasher.finalize())
+}
+
+pub fn diff(original_filename: &Path, modified_filename: &Path) -> Result<()> {
+    let read = |name: &Path| -> Result<Vec<u8>> {
+        let mut f = std::fs::File::open(name)?;
+        let mut buf = Vec::new();
+        f.read_to_end(&mut buf)?;
+        Ok(buf)
+    };
+
+    let original = read(original_filename)?;
+    let modified = read(modified_filename)?;
+
+    let patch = Patch::create(&original, &modified)?;
+
+    let stdout = std::io::stdout();
+    patch.write(&mut stdout.lock())?;
+
+    Ok(())
+}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    /// Serialize a patch and parse it back through `write`/`parse`, panicking
+    /// if either step fails.
+    fn reserialize(patch: &Patch) -> Patch {
+        let mut bytes = Vec::new();
+        patch.write(&mut bytes).unwrap();
+        Patch::parse(&bytes).unwrap()
+    }
+
+    /// Assert `create` produces a patch that reconstructs `modified` from
+    /// `original`, both directly and after a serialize/parse round-trip.
+    fn assert_roundtrip(original: &[u8], modified: &[u8]) {
+        let patch = Patch::create(original, modified).unwrap();
+        patch.validate(original.len()).unwrap();
+        assert_eq!(patch.apply(original).unwrap(), modified);
+        assert_eq!(reserialize(&patch).apply(original).unwrap(), modified);
+    }
+
+    #[test]
+    fn roundtrip_simple_edit() {
+        assert_roundtrip(b"hello world", b"hello rust!");
+    }
+
+    #[test]
+    fn roundtrip_extends_input() {
+        // The most common IPS case: the target is longer than the source and
+        // the last original byte also differs, so the run straddles the old end.
+        assert_roundtrip(b"AB", b"AXYZ");
+        assert_roundtrip(b"", b"brand new content");
+    }
+
+    #[test]
+    fn roundtrip_rle_run() {
+        let mut modified = vec![0u8; 4096];
+        modified[10..2000].iter_mut().for_each(|b| *b = 0xAA);
+        let original = vec![0u8; 4096];
+        let patch = Patch::create(&original, &modified).unwrap();
+        assert!(patch
+            .records
+            .iter()
+            .any(|r| matches!(r, Record::RuntimeLengthEncoded { .. })));
+        assert_eq!(patch.apply(&original).unwrap(), modified);
+    }
+
+    #[test]
+    fn truncation_shrinks_output() {
+        let original = b"the quick brown fox";
+        let modified = b"the quick";
+        let patch = Patch::create(original, modified).unwrap();
+        assert_eq!(patch.truncation, Some(modified.len()));
+        assert_eq!(patch.apply(original).unwrap(), modified);
+    }
+
+    #[test]
+    fn eof_offset_is_shifted_not_emitted_as_marker() {
+        const EOF_OFFSET: usize = 0x454F46;
+        let original = vec![0u8; EOF_OFFSET + 8];
+        let mut modified = original.clone();
+        // A differing run straddling the "EOF" offset must not emit a record
+        // whose offset is the end marker, and must not overlap itself.
+        for b in modified[EOF_OFFSET - 2..EOF_OFFSET + 4].iter_mut() {
+            *b = 0x42;
+        }
+        let patch = Patch::create(&original, &modified).unwrap();
+        patch.validate(original.len()).unwrap();
+        assert!(patch.records.iter().all(|r| {
+            let offset = match r {
+                Record::Normal { offset, .. } => *offset,
+                Record::RuntimeLengthEncoded { offset, .. } => *offset,
+            };
+            offset != EOF_OFFSET
+        }));
+        assert_eq!(patch.apply(&original).unwrap(), modified);
+    }
+
+    #[test]
+    fn validate_rejects_overlap_and_gaps() {
+        // Two records writing the same address overlap.
+        let overlapping = Patch {
+            records: vec![
+                Record::Normal {
+                    offset: 0,
+                    data: vec![1, 2, 3],
+                },
+                Record::Normal {
+                    offset: 2,
+                    data: vec![9],
+                },
+            ],
+            truncation: None,
+        };
+        assert!(overlapping.validate(8).is_err());
+
+        // A record starting past the reachable end leaves a gap.
+        let gap = Patch {
+            records: vec![Record::Normal {
+                offset: 10,
+                data: vec![1],
+            }],
+            truncation: None,
+        };
+        assert!(gap.validate(4).is_err());
+    }
+
+    #[test]
+    fn apply_checked_verifies_crc() {
+        let original = b"source rom";
+        let modified = b"patched rom";
+        let patch = Patch::create(original, modified).unwrap();
+        let src_crc = crate::crc32::crc32(original);
+        assert!(patch
+            .apply_checked(original, Some(src_crc), None)
+            .is_ok());
+        assert!(patch
+            .apply_checked(original, Some(src_crc ^ 0xFFFF), None)
+            .is_err());
+    }
+}
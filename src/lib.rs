@@ -0,0 +1,3 @@
+pub mod crc32;
+pub mod error;
+pub mod ips;
@@ -1,23 +1,81 @@
+use eyre::{eyre, Result};
+use ips_patch::ips;
 use std::path::PathBuf;
 use structopt::StructOpt;
 
-mod error;
-mod ips;
-
 /// ips-patch: IPS patch tool
-///
-/// Applies patch to data read from stdin, writes output to stdout.
 #[derive(StructOpt, Debug)]
 #[structopt(name = "ips-patch")]
-struct Opt {
-    #[structopt(name = "FILE", parse(from_os_str))]
+enum Opt {
+    /// Apply a patch to data read from stdin, writing output to stdout, or
+    /// from the --in file to the --out file.
+    Apply {
+        #[structopt(name = "FILE", parse(from_os_str))]
+        arg_patch: PathBuf,
+        /// Validate records (bounds, range, overlap) before applying.
+        #[structopt(long = "strict")]
+        strict: bool,
+        /// Expected CRC32 (hex) of the source ROM; verified before patching.
+        #[structopt(long = "verify-in", parse(try_from_str = parse_crc))]
+        verify_in: Option<u32>,
+        /// Expected CRC32 (hex) of the output ROM; verified after patching.
+        #[structopt(long = "verify-out", parse(try_from_str = parse_crc))]
+        verify_out: Option<u32>,
+        /// Input ROM file; enables file-oriented mode (requires --out).
+        #[structopt(long = "in", parse(from_os_str))]
+        arg_in: Option<PathBuf>,
+        /// Output ROM file; enables file-oriented mode (requires --in).
+        #[structopt(long = "out", parse(from_os_str))]
+        arg_out: Option<PathBuf>,
+    },
+    /// Create a patch from ORIGINAL to MODIFIED, writing IPS to stdout.
+    Create {
+        #[structopt(name = "ORIGINAL", parse(from_os_str))]
+        arg_original: PathBuf,
+        #[structopt(name = "MODIFIED", parse(from_os_str))]
+        arg_modified: PathBuf,
+    },
+}
+
+fn apply(
     arg_patch: PathBuf,
+    strict: bool,
+    verify_in: Option<u32>,
+    verify_out: Option<u32>,
+    arg_in: Option<PathBuf>,
+    arg_out: Option<PathBuf>,
+) -> Result<()> {
+    match (arg_in, arg_out) {
+        (Some(input), Some(output)) => {
+            ips::patch_stream(&arg_patch, &input, &output, strict, verify_in, verify_out)
+        }
+        (None, None) => ips::patch(&arg_patch, strict, verify_in, verify_out),
+        _ => Err(eyre!("--in and --out must be supplied together")),
+    }
+}
+
+/// Parses a CRC32 argument as hexadecimal, tolerating an optional `0x` prefix.
+fn parse_crc(s: &str) -> Result<u32, std::num::ParseIntError> {
+    u32::from_str_radix(s.trim_start_matches("0x").trim_start_matches("0X"), 16)
 }
 
 fn main() {
-    let args = Opt::from_args();
+    let result = match Opt::from_args() {
+        Opt::Apply {
+            arg_patch,
+            strict,
+            verify_in,
+            verify_out,
+            arg_in,
+            arg_out,
+        } => apply(arg_patch, strict, verify_in, verify_out, arg_in, arg_out),
+        Opt::Create {
+            arg_original,
+            arg_modified,
+        } => ips::diff(&arg_original, &arg_modified),
+    };
 
-    match ips::patch(&args.arg_patch) {
+    match result {
         Ok(_) => (),
         Err(e) => {
             use std::io::Write;